@@ -0,0 +1,76 @@
+use serde_derive::Deserialize;
+use std::fmt;
+
+#[derive(Debug, Deserialize)]
+#[serde(untagged)]
+pub enum Error {
+    PubSub {
+        code: u16,
+        status: String,
+        message: String,
+    },
+    #[serde(skip)]
+    Http(String),
+    #[serde(skip)]
+    Json(String),
+    #[serde(skip)]
+    Decode(String),
+    #[serde(skip)]
+    SchemaViolation { errors: Vec<String> },
+    /// Per-chunk ack failures from `try_acknowledge_messages`, as `(chunk index, reason)`.
+    /// `reason` is either `"status <code>"` for a non-success response or the transport
+    /// error's message, so callers can tell the two apart instead of both collapsing into
+    /// an indistinguishable sentinel.
+    #[serde(skip)]
+    AckFailure { failed_chunks: Vec<(usize, String)> },
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Error::PubSub {
+                code,
+                status,
+                message,
+            } => write!(f, "PubSub error {} ({}): {}", code, status, message),
+            Error::Http(message) => write!(f, "HTTP error: {}", message),
+            Error::Json(message) => write!(f, "JSON error: {}", message),
+            Error::Decode(message) => write!(f, "base64 decode error: {}", message),
+            Error::SchemaViolation { errors } => {
+                write!(f, "message failed schema validation: {}", errors.join(", "))
+            }
+            Error::AckFailure { failed_chunks } => {
+                let reasons: Vec<String> = failed_chunks
+                    .iter()
+                    .map(|(index, reason)| format!("chunk {}: {}", index, reason))
+                    .collect();
+                write!(
+                    f,
+                    "{} of the ack request's chunks failed ({})",
+                    failed_chunks.len(),
+                    reasons.join(", ")
+                )
+            }
+        }
+    }
+}
+
+impl std::error::Error for Error {}
+
+impl From<hyper::Error> for Error {
+    fn from(err: hyper::Error) -> Self {
+        Error::Http(err.to_string())
+    }
+}
+
+impl From<serde_json::Error> for Error {
+    fn from(err: serde_json::Error) -> Self {
+        Error::Json(err.to_string())
+    }
+}
+
+impl From<base64::DecodeError> for Error {
+    fn from(err: base64::DecodeError) -> Self {
+        Error::Decode(err.to_string())
+    }
+}
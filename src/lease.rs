@@ -0,0 +1,94 @@
+use crate::client::Client;
+use crate::retry::RetryPolicy;
+use crate::subscription::send_modify_ack_deadline;
+use std::time::Duration;
+use tokio::sync::oneshot;
+use tokio::task::JoinHandle;
+use tokio::time::Instant;
+
+/// Keeps a batch of pulled messages leased past their ack deadline.
+///
+/// While held, a background task periodically calls `:modifyAckDeadline` to push the
+/// deadline back, so a handler that takes longer than the subscription's ack deadline to
+/// run doesn't get its messages redelivered out from under it. The extension loop gives up
+/// once `max_lease_duration` has elapsed, on the assumption that a handler running longer
+/// than that has stalled. Drop the lease (or call `release`) once the caller has acked or
+/// nacked the batch.
+pub struct AckLease {
+    ack_ids: Vec<String>,
+    stop: Option<oneshot::Sender<()>>,
+    handle: Option<JoinHandle<()>>,
+}
+
+impl AckLease {
+    pub(crate) fn spawn(
+        client: Client,
+        name: String,
+        retry_policy: RetryPolicy,
+        ack_ids: Vec<String>,
+        extension_interval: Duration,
+        deadline_seconds: i32,
+        max_lease_duration: Duration,
+    ) -> Self {
+        let (stop_tx, mut stop_rx) = oneshot::channel();
+        let lease_ids = ack_ids.clone();
+
+        let handle = tokio::spawn(async move {
+            let started_at = Instant::now();
+
+            loop {
+                tokio::select! {
+                    _ = tokio::time::sleep(extension_interval) => {
+                        if started_at.elapsed() >= max_lease_duration {
+                            break;
+                        }
+                        if send_modify_ack_deadline(
+                            &client,
+                            &name,
+                            &retry_policy,
+                            lease_ids.clone(),
+                            deadline_seconds,
+                        )
+                        .await
+                        .is_err()
+                        {
+                            break;
+                        }
+                    }
+                    _ = &mut stop_rx => break,
+                }
+            }
+        });
+
+        AckLease {
+            ack_ids,
+            stop: Some(stop_tx),
+            handle: Some(handle),
+        }
+    }
+
+    /// The ack IDs covered by this lease.
+    pub fn ack_ids(&self) -> &[String] {
+        &self.ack_ids
+    }
+
+    /// Stops extending the lease, e.g. once the caller has acked or nacked the batch.
+    pub fn release(mut self) {
+        self.stop();
+    }
+
+    fn stop(&mut self) {
+        if let Some(stop) = self.stop.take() {
+            let _ = stop.send(());
+        }
+        if let Some(handle) = self.handle.take() {
+            handle.abort();
+        }
+    }
+}
+
+impl Drop for AckLease {
+    fn drop(&mut self) {
+        self.stop();
+    }
+}
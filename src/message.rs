@@ -1,4 +1,5 @@
 use crate::error;
+use crate::schema::SchemaValidator;
 use base64;
 use serde_derive::{Deserialize, Serialize};
 use std::collections::HashMap;
@@ -12,6 +13,7 @@ pub struct EncodedMessage {
     pub(crate) publish_time: Option<String>,
     #[serde(skip_serializing)]
     pub(crate) message_id: Option<String>,
+    pub(crate) ordering_key: Option<String>,
 }
 
 pub trait FromPubSubMessage
@@ -34,8 +36,38 @@ impl EncodedMessage {
             publish_time: None,
             message_id: None,
             attributes: None,
+            ordering_key: None,
         }
     }
+
+    /// Like [`EncodedMessage::new`], but tags the message with `ordering_key` so subscribers
+    /// can process messages that share a key in the order they were published.
+    pub fn new_ordered<T: serde::Serialize>(data: &T, ordering_key: String) -> Self {
+        EncodedMessage {
+            ordering_key: Some(ordering_key),
+            ..EncodedMessage::new(data)
+        }
+    }
+
+    /// Like [`EncodedMessage::new`], but validates `data` against `validator` first so
+    /// producers can reject malformed payloads before they're ever sent.
+    pub fn new_validated<T: serde::Serialize>(
+        data: &T,
+        validator: &SchemaValidator,
+    ) -> Result<Self, error::Error> {
+        let value = serde_json::to_value(data)?;
+        validator.validate(&value)?;
+
+        let json = serde_json::to_string(&value)?;
+        let data = base64::encode(&json);
+        Ok(EncodedMessage {
+            data,
+            publish_time: None,
+            message_id: None,
+            attributes: None,
+            ordering_key: None,
+        })
+    }
 }
 
 #[derive(Deserialize, Debug)]
@@ -52,6 +84,7 @@ pub struct RawMessage {
     pub publish_time: Option<String>,
     pub message_id: Option<String>,
     pub data: String,
+    pub ordering_key: Option<String>,
 }
 
 impl From<Message> for RawMessage {
@@ -62,6 +95,50 @@ impl From<Message> for RawMessage {
             publish_time: msg.message.publish_time,
             message_id: msg.message.message_id,
             data: msg.message.data,
+            ordering_key: msg.message.ordering_key,
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn new_ordered_carries_the_ordering_key_and_decodes_the_same_payload_as_new() {
+        let payload = json!({ "event": "created" });
+
+        let plain = EncodedMessage::new(&payload);
+        let ordered = EncodedMessage::new_ordered(&payload, "order-1".to_string());
+
+        assert_eq!(ordered.ordering_key, Some("order-1".to_string()));
+        assert_eq!(ordered.decode().unwrap(), plain.decode().unwrap());
+    }
+
+    #[test]
+    fn new_validated_accepts_a_payload_matching_the_schema() {
+        let validator = SchemaValidator::new(json!({
+            "type": "object",
+            "required": ["event"],
+        }))
+        .unwrap();
+
+        let message = EncodedMessage::new_validated(&json!({ "event": "created" }), &validator);
+
+        assert!(message.is_ok());
+    }
+
+    #[test]
+    fn new_validated_rejects_a_payload_that_violates_the_schema() {
+        let validator = SchemaValidator::new(json!({
+            "type": "object",
+            "required": ["event"],
+        }))
+        .unwrap();
+
+        let err = EncodedMessage::new_validated(&json!({}), &validator).unwrap_err();
+
+        assert!(matches!(err, error::Error::SchemaViolation { .. }));
+    }
+}
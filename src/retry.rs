@@ -0,0 +1,118 @@
+use crate::client::Client;
+use crate::error;
+use hyper::{Body, Request, Response};
+use rand::Rng;
+use std::time::Duration;
+
+/// Exponential backoff with jitter for transient pull/ack/modify/delete failures.
+///
+/// `max_retries: 0` (the default) keeps today's single-attempt behavior. With retries
+/// configured, a failed attempt sleeps for `base_delay * multiplier^attempt` plus up to
+/// `base_delay` of random jitter before trying again, as long as the response status is in
+/// `retryable_statuses` (or the attempt failed at the transport level).
+#[derive(Debug, Clone)]
+pub struct RetryPolicy {
+    pub max_retries: u32,
+    pub base_delay: Duration,
+    pub multiplier: f64,
+    pub retryable_statuses: Vec<u16>,
+}
+
+impl RetryPolicy {
+    pub fn new(max_retries: u32, base_delay: Duration, multiplier: f64) -> Self {
+        RetryPolicy {
+            max_retries,
+            base_delay,
+            multiplier,
+            retryable_statuses: vec![429, 500, 503],
+        }
+    }
+
+    fn is_retryable_status(&self, status: u16) -> bool {
+        self.retryable_statuses.contains(&status)
+    }
+
+    fn delay_for(&self, attempt: u32) -> Duration {
+        let exponent = self.multiplier.powi(attempt as i32);
+        let base_millis = self.base_delay.as_millis() as f64;
+        let millis = base_millis * exponent;
+        let jitter: f64 = if base_millis == 0.0 {
+            0.0
+        } else {
+            rand::thread_rng().gen_range(0.0..base_millis)
+        };
+        Duration::from_millis((millis + jitter) as u64)
+    }
+}
+
+impl Default for RetryPolicy {
+    /// No retries: a single attempt, matching the crate's historical behavior.
+    fn default() -> Self {
+        RetryPolicy::new(0, Duration::from_millis(200), 2.0)
+    }
+}
+
+/// Sends a request built by `build_request`, retrying on a retryable status code or
+/// transport error according to `policy`. `build_request` is called again on every retry,
+/// since a `hyper::Request` can't be resent once consumed.
+pub(crate) async fn send_with_retry(
+    client: &Client,
+    policy: &RetryPolicy,
+    mut build_request: impl FnMut() -> Request<Body>,
+) -> Result<Response<Body>, error::Error> {
+    let mut attempt = 0;
+
+    loop {
+        let req = build_request();
+        match client.hyper_client().request(req).await {
+            Ok(response)
+                if attempt < policy.max_retries
+                    && policy.is_retryable_status(response.status().as_u16()) =>
+            {
+                tokio::time::sleep(policy.delay_for(attempt)).await;
+                attempt += 1;
+            }
+            Ok(response) => return Ok(response),
+            Err(_err) if attempt < policy.max_retries => {
+                tokio::time::sleep(policy.delay_for(attempt)).await;
+                attempt += 1;
+            }
+            Err(err) => return Err(err.into()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn delay_for_grows_exponentially_with_attempt() {
+        let policy = RetryPolicy::new(5, Duration::from_millis(1000), 2.0);
+
+        // With zero jitter range this would be exactly base_delay * multiplier^attempt;
+        // jitter only adds up to one more base_delay, so the lower bound still holds.
+        assert!(policy.delay_for(0).as_millis() >= 1000);
+        assert!(policy.delay_for(1).as_millis() >= 2000);
+        assert!(policy.delay_for(2).as_millis() >= 4000);
+    }
+
+    #[test]
+    fn delay_for_does_not_panic_on_zero_base_delay() {
+        let policy = RetryPolicy::new(3, Duration::ZERO, 2.0);
+
+        for attempt in 0..5 {
+            assert_eq!(policy.delay_for(attempt), Duration::ZERO);
+        }
+    }
+
+    #[test]
+    fn is_retryable_status_matches_default_set() {
+        let policy = RetryPolicy::default();
+
+        assert!(policy.is_retryable_status(429));
+        assert!(policy.is_retryable_status(500));
+        assert!(policy.is_retryable_status(503));
+        assert!(!policy.is_retryable_status(404));
+    }
+}
@@ -0,0 +1,71 @@
+use crate::error;
+use valico::json_schema;
+
+/// Validates decoded message payloads against a JSON Schema, compiled once and reused
+/// across calls. Attach one to a [`crate::subscription::Subscription`] to reject malformed
+/// messages in `get_messages` before they ever reach `FromPubSubMessage::from`.
+pub struct SchemaValidator {
+    scope: json_schema::Scope,
+    schema_id: url::Url,
+}
+
+impl SchemaValidator {
+    pub fn new(schema: serde_json::Value) -> Result<Self, error::Error> {
+        let mut scope = json_schema::Scope::new();
+        let schema_id =
+            scope
+                .compile(schema, false)
+                .map_err(|e| error::Error::SchemaViolation {
+                    errors: vec![format!("{:?}", e)],
+                })?;
+
+        Ok(SchemaValidator { scope, schema_id })
+    }
+
+    pub fn validate(&self, document: &serde_json::Value) -> Result<(), error::Error> {
+        let schema = self
+            .scope
+            .resolve(&self.schema_id)
+            .expect("schema was compiled into this scope");
+
+        let state = schema.validate(document);
+        if state.is_valid() {
+            Ok(())
+        } else {
+            let errors = state
+                .errors
+                .into_iter()
+                .map(|e| e.get_title().to_string())
+                .collect();
+            Err(error::Error::SchemaViolation { errors })
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn name_schema() -> SchemaValidator {
+        SchemaValidator::new(json!({
+            "type": "object",
+            "required": ["name"],
+            "properties": { "name": { "type": "string" } },
+        }))
+        .unwrap()
+    }
+
+    #[test]
+    fn validate_accepts_a_matching_document() {
+        let validator = name_schema();
+        assert!(validator.validate(&json!({ "name": "ferris" })).is_ok());
+    }
+
+    #[test]
+    fn validate_rejects_a_document_missing_a_required_field() {
+        let validator = name_schema();
+        let err = validator.validate(&json!({})).unwrap_err();
+        assert!(matches!(err, crate::error::Error::SchemaViolation { .. }));
+    }
+}
@@ -1,11 +1,18 @@
 use crate::client::Client;
 use crate::error;
-use crate::message::{FromPubSubMessage, Message, RawMessage};
+use crate::lease::AckLease;
+use crate::message::{EncodedMessage, FromPubSubMessage, Message, RawMessage};
+use crate::retry::{self, RetryPolicy};
+use crate::schema::SchemaValidator;
+use futures::stream::{self, Stream};
 use hyper::body::Buf;
 use hyper::{Method, StatusCode};
 use lazy_static::lazy_static;
 use serde_derive::{Deserialize, Serialize};
+use std::collections::{HashMap, VecDeque};
 use std::env;
+use std::sync::Arc;
+use std::time::Duration;
 
 lazy_static! {
     static ref PUBSUB_HOST: String = env::var("PUBSUB_EMULATOR_HOST")
@@ -13,6 +20,21 @@ lazy_static! {
         .unwrap_or_else(|_| String::from("https://pubsub.googleapis.com"));
 }
 
+/// How long `Subscription::stream`/`stream_raw` wait before re-pulling after an empty batch.
+const DEFAULT_POLL_INTERVAL: Duration = Duration::from_millis(500);
+
+/// How often `pull_with_lease` extends the ack deadline of an in-flight batch.
+const DEFAULT_LEASE_EXTENSION_INTERVAL: Duration = Duration::from_secs(20);
+
+/// How many seconds each lease extension pushes the ack deadline out by.
+const DEFAULT_LEASE_EXTENSION_SECONDS: i32 = 30;
+
+/// How long `pull_with_lease` keeps extending a batch before giving up on it.
+const DEFAULT_MAX_LEASE_DURATION: Duration = Duration::from_secs(10 * 60);
+
+/// How many ack IDs `try_acknowledge_messages` puts in a single `:acknowledge` request.
+const DEFAULT_ACK_CHUNK_SIZE: usize = 1000;
+
 #[derive(Deserialize, Debug)]
 struct Response {
     #[serde(alias = "receivedMessages")]
@@ -22,10 +44,56 @@ struct Response {
 
 #[derive(Serialize)]
 struct AckRequest {
-    #[serde(alias = "ackIds")]
+    #[serde(rename = "ackIds")]
     ack_ids: Vec<String>,
 }
 
+#[derive(Serialize)]
+struct ModifyAckDeadlineRequest {
+    #[serde(rename = "ackIds")]
+    ack_ids: Vec<String>,
+    #[serde(rename = "ackDeadlineSeconds")]
+    ack_deadline_seconds: i32,
+}
+
+/// Sends a `:modifyAckDeadline` request. Takes its dependencies by value/reference instead
+/// of a whole `Subscription` so [`AckLease`]'s background task doesn't have to capture
+/// subscription state (e.g. a schema validator) that isn't `Send`.
+pub(crate) async fn send_modify_ack_deadline(
+    client: &Client,
+    name: &str,
+    retry_policy: &RetryPolicy,
+    ids: Vec<String>,
+    ack_deadline_seconds: i32,
+) -> Result<(), error::Error> {
+    let uri: hyper::Uri = format!("{}/v1/{}:modifyAckDeadline", *PUBSUB_HOST, name)
+        .parse()
+        .unwrap();
+
+    let json = serde_json::to_string(&ModifyAckDeadlineRequest {
+        ack_ids: ids,
+        ack_deadline_seconds,
+    })
+    .unwrap();
+
+    let response = retry::send_with_retry(client, retry_policy, || {
+        let mut req = client.request(Method::POST, json.clone());
+        *req.uri_mut() = uri.clone();
+        req
+    })
+    .await?;
+
+    if response.status().is_success() {
+        Ok(())
+    } else {
+        Err(error::Error::PubSub {
+            code: response.status().as_u16(),
+            status: response.status().to_string(),
+            message: format!("modifyAckDeadline for '{}' failed", name),
+        })
+    }
+}
+
 #[derive(Deserialize, Serialize, Clone)]
 pub struct Subscription {
     #[serde(skip_serializing)]
@@ -33,6 +101,25 @@ pub struct Subscription {
     pub topic: Option<String>,
     #[serde(skip)]
     pub max_messages: usize,
+    #[serde(skip)]
+    pub poll_interval: Duration,
+    #[serde(skip)]
+    pub lease_extension_interval: Duration,
+    #[serde(skip)]
+    pub lease_extension_seconds: i32,
+    #[serde(skip)]
+    pub max_lease_duration: Duration,
+    /// Validates each message's decoded payload against a JSON Schema before it's handed
+    /// to `FromPubSubMessage::from`. Off (`None`) by default to preserve existing behavior.
+    #[serde(skip)]
+    pub schema_validator: Option<Arc<SchemaValidator>>,
+    /// Backoff/retry behavior for pull, ack, modify-ack-deadline and delete requests.
+    /// Defaults to a single attempt, matching the crate's historical behavior.
+    #[serde(skip)]
+    pub retry_policy: RetryPolicy,
+    /// Max ack IDs per `:acknowledge` request issued by `try_acknowledge_messages`.
+    #[serde(skip)]
+    pub ack_chunk_size: usize,
 
     #[serde(skip)]
     pub(crate) client: Option<Client>,
@@ -44,10 +131,31 @@ impl Subscription {
             name,
             topic,
             max_messages: 100,
+            poll_interval: DEFAULT_POLL_INTERVAL,
+            lease_extension_interval: DEFAULT_LEASE_EXTENSION_INTERVAL,
+            lease_extension_seconds: DEFAULT_LEASE_EXTENSION_SECONDS,
+            max_lease_duration: DEFAULT_MAX_LEASE_DURATION,
+            schema_validator: None,
+            retry_policy: RetryPolicy::default(),
+            ack_chunk_size: DEFAULT_ACK_CHUNK_SIZE,
             client,
         }
     }
+
+    /// Best-effort acknowledge: logs and swallows failures. Prefer
+    /// [`Subscription::try_acknowledge_messages`] when callers need to know which chunks
+    /// failed, e.g. to avoid double-processing.
     pub async fn acknowledge_messages(&self, ids: Vec<String>) {
+        if let Err(e) = self.try_acknowledge_messages(ids).await {
+            log::error!("Failed ACK: {}", e);
+        }
+    }
+
+    /// Acknowledges `ids`, split into requests of at most `ack_chunk_size` ack IDs (the API
+    /// rejects overly large batches). Returns `Err(error::Error::AckFailure)` naming each
+    /// failed chunk's status code or transport error if any did, so callers can tell a permanent
+    /// ack failure from a success instead of it being silently logged away.
+    pub async fn try_acknowledge_messages(&self, ids: Vec<String>) -> Result<(), error::Error> {
         let client = self
             .client
             .as_ref()
@@ -57,16 +165,97 @@ impl Subscription {
             .parse()
             .unwrap();
 
-        let json = serde_json::to_string(&AckRequest { ack_ids: ids }).unwrap();
+        let mut failed_chunks = Vec::new();
 
-        let mut req = client.request(Method::POST, json);
-        *req.uri_mut() = uri.clone();
+        for (index, chunk) in Self::chunk_ack_ids(&ids, self.ack_chunk_size).enumerate() {
+            let json = serde_json::to_string(&AckRequest {
+                ack_ids: chunk.to_vec(),
+            })
+            .unwrap();
 
-        if let Err(e) = client.hyper_client().request(req).await {
-            log::error!("Failed ACK: {}", e);
+            let result = retry::send_with_retry(client, &self.retry_policy, || {
+                let mut req = client.request(Method::POST, json.clone());
+                *req.uri_mut() = uri.clone();
+                req
+            })
+            .await;
+
+            match result {
+                Ok(response) if response.status().is_success() => {}
+                Ok(response) => {
+                    failed_chunks.push((index, format!("status {}", response.status().as_u16())))
+                }
+                Err(e) => failed_chunks.push((index, e.to_string())),
+            }
+        }
+
+        if failed_chunks.is_empty() {
+            Ok(())
+        } else {
+            Err(error::Error::AckFailure { failed_chunks })
         }
     }
 
+    /// Splits `ids` into `:acknowledge`-sized chunks of at most `chunk_size` (treating 0 as 1,
+    /// so a misconfigured chunk size degrades to one ID per request rather than panicking).
+    fn chunk_ack_ids(ids: &[String], chunk_size: usize) -> std::slice::Chunks<String> {
+        ids.chunks(chunk_size.max(1))
+    }
+
+    pub(crate) async fn modify_ack_deadline(
+        &self,
+        ids: Vec<String>,
+        ack_deadline_seconds: i32,
+    ) -> Result<(), error::Error> {
+        let client = self
+            .client
+            .as_ref()
+            .expect("Subscription was not created using a client");
+
+        send_modify_ack_deadline(
+            client,
+            &self.name,
+            &self.retry_policy,
+            ids,
+            ack_deadline_seconds,
+        )
+        .await
+    }
+
+    /// Returns a message to the queue immediately by setting its ack deadline to zero,
+    /// instead of waiting for it to expire on its own.
+    pub async fn nack(&self, ids: Vec<String>) -> Result<(), error::Error> {
+        self.modify_ack_deadline(ids, 0).await
+    }
+
+    /// Pulls a batch of messages and leases them: a background task keeps pushing their
+    /// ack deadline back until the returned [`AckLease`] is released, so handlers that run
+    /// longer than the subscription's ack deadline don't get their messages redelivered.
+    /// Ack or nack the ack IDs yourself once done, then drop (or `release`) the lease.
+    pub async fn pull_with_lease<T: FromPubSubMessage>(
+        &self,
+    ) -> Result<(Vec<(Result<T, error::Error>, String)>, AckLease), error::Error> {
+        let messages = self.get_messages::<T>().await?;
+        let ack_ids = messages.iter().map(|(_, id)| id.clone()).collect();
+
+        let client = self
+            .client
+            .clone()
+            .expect("Subscription was not created using a client");
+
+        let lease = AckLease::spawn(
+            client,
+            self.name.clone(),
+            self.retry_policy.clone(),
+            ack_ids,
+            self.lease_extension_interval,
+            self.lease_extension_seconds,
+            self.max_lease_duration,
+        );
+
+        Ok((messages, lease))
+    }
+
     async fn request_messages(&self) -> Result<Response, error::Error> {
         let client = self
             .client
@@ -79,10 +268,12 @@ impl Subscription {
 
         let json = format!(r#"{{ "maxMessages": {} }}"#, self.max_messages);
 
-        let mut req = client.request(Method::POST, json);
-        *req.uri_mut() = uri.clone();
-
-        let response = client.hyper_client().request(req).await?;
+        let response = retry::send_with_retry(client, &self.retry_policy, || {
+            let mut req = client.request(Method::POST, json.clone());
+            *req.uri_mut() = uri.clone();
+            req
+        })
+        .await?;
 
         if response.status() == StatusCode::NOT_FOUND {
             return Err(error::Error::PubSub {
@@ -112,11 +303,23 @@ impl Subscription {
             .received_messages
             .unwrap_or_default()
             .into_iter()
-            .map(|m| (T::from(m.message), m.ack_id))
+            .map(|m| (self.validate_and_decode::<T>(m.message), m.ack_id))
             .collect();
         Ok(messages)
     }
 
+    fn validate_and_decode<T: FromPubSubMessage>(
+        &self,
+        message: EncodedMessage,
+    ) -> Result<T, error::Error> {
+        if let Some(validator) = &self.schema_validator {
+            let bytes = message.decode()?;
+            let document: serde_json::Value = serde_json::from_slice(&bytes)?;
+            validator.validate(&document)?;
+        }
+        T::from(message)
+    }
+
     pub async fn get_messages_raw(&self) -> Result<Vec<RawMessage>, error::Error> {
         let response: Response = match self.request_messages().await {
             Ok(response) => response,
@@ -136,7 +339,104 @@ impl Subscription {
         Ok(messages)
     }
 
+    /// Turns this subscription into an unbounded stream of decoded messages, transparently
+    /// re-pulling with `request_messages` whenever the internal buffer runs dry and backing
+    /// off for `poll_interval` when a pull comes back empty, so callers don't have to write
+    /// their own polling loop.
+    pub fn stream<T: FromPubSubMessage>(
+        self,
+    ) -> impl Stream<Item = (Result<T, error::Error>, String)> {
+        stream::unfold(
+            (self, VecDeque::new()),
+            |(subscription, mut buffer)| async move {
+                loop {
+                    if let Some(item) = buffer.pop_front() {
+                        return Some((item, (subscription, buffer)));
+                    }
+
+                    let result = subscription.get_messages::<T>().await;
+                    if Self::should_backoff(&result) {
+                        tokio::time::sleep(subscription.poll_interval).await;
+                    }
+
+                    match result {
+                        Ok(messages) if messages.is_empty() => {}
+                        Ok(messages) => buffer.extend(messages),
+                        Err(err) => {
+                            return Some(((Err(err), String::new()), (subscription, buffer)));
+                        }
+                    }
+                }
+            },
+        )
+    }
+
+    /// Whether a pull result should trigger the poll-interval backoff before the next
+    /// attempt: an empty batch (nothing ready yet) and a failed pull both wait, while a
+    /// non-empty batch proceeds immediately.
+    fn should_backoff<T>(result: &Result<Vec<T>, error::Error>) -> bool {
+        match result {
+            Ok(messages) => messages.is_empty(),
+            Err(_) => true,
+        }
+    }
+
+    /// Groups a pulled batch by `ordering_key`, preserving each key's arrival order.
+    /// Messages without an ordering key are grouped under `None`.
+    pub fn group_by_ordering_key(
+        messages: Vec<RawMessage>,
+    ) -> HashMap<Option<String>, Vec<RawMessage>> {
+        let mut groups: HashMap<Option<String>, Vec<RawMessage>> = HashMap::new();
+        for message in messages {
+            groups
+                .entry(message.ordering_key.clone())
+                .or_default()
+                .push(message);
+        }
+        groups
+    }
+
+    /// Like [`Subscription::group_by_ordering_key`], but wraps each key's messages in its
+    /// own stream, so downstream code can process a key's sub-stream serially (preserving
+    /// order) while driving different keys' sub-streams concurrently.
+    pub fn ordered_streams(
+        messages: Vec<RawMessage>,
+    ) -> HashMap<Option<String>, impl Stream<Item = RawMessage>> {
+        Self::group_by_ordering_key(messages)
+            .into_iter()
+            .map(|(key, group)| (key, stream::iter(group)))
+            .collect()
+    }
+
+    /// Raw-message counterpart of [`Subscription::stream`].
+    pub fn stream_raw(self) -> impl Stream<Item = Result<RawMessage, error::Error>> {
+        stream::unfold(
+            (self, VecDeque::new()),
+            |(subscription, mut buffer)| async move {
+                loop {
+                    if let Some(item) = buffer.pop_front() {
+                        return Some((Ok(item), (subscription, buffer)));
+                    }
+
+                    let result = subscription.get_messages_raw().await;
+                    if Self::should_backoff(&result) {
+                        tokio::time::sleep(subscription.poll_interval).await;
+                    }
+
+                    match result {
+                        Ok(messages) if messages.is_empty() => {}
+                        Ok(messages) => buffer.extend(messages),
+                        Err(err) => {
+                            return Some((Err(err), (subscription, buffer)));
+                        }
+                    }
+                }
+            },
+        )
+    }
+
     pub async fn destroy(self) -> Result<(), error::Error> {
+        let retry_policy = self.retry_policy.clone();
         let client = self
             .client
             .expect("Subscription was not created using a client");
@@ -145,17 +445,106 @@ impl Subscription {
             .parse()
             .unwrap();
 
-        let mut req = client.request(Method::DELETE, "");
-        *req.uri_mut() = uri.clone();
-
-        if let Err(e) = client.hyper_client().request(req).await {
-            Err(e.into())
-        } else {
-            Ok(())
-        }
+        retry::send_with_retry(&client, &retry_policy, || {
+            let mut req = client.request(Method::DELETE, "");
+            *req.uri_mut() = uri.clone();
+            req
+        })
+        .await?;
+        Ok(())
     }
 
     pub fn client(&self) -> &Client {
         self.client.as_ref().unwrap()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn raw_message(ack_id: &str, ordering_key: Option<&str>) -> RawMessage {
+        RawMessage {
+            ack_id: ack_id.to_string(),
+            attributes: None,
+            publish_time: None,
+            message_id: None,
+            data: String::new(),
+            ordering_key: ordering_key.map(String::from),
+        }
+    }
+
+    #[test]
+    fn group_by_ordering_key_preserves_per_key_order() {
+        let messages = vec![
+            raw_message("1", Some("a")),
+            raw_message("2", Some("b")),
+            raw_message("3", Some("a")),
+            raw_message("4", None),
+        ];
+
+        let groups = Subscription::group_by_ordering_key(messages);
+
+        let a: Vec<&str> = groups[&Some("a".to_string())]
+            .iter()
+            .map(|m| m.ack_id.as_str())
+            .collect();
+        assert_eq!(a, vec!["1", "3"]);
+
+        let b: Vec<&str> = groups[&Some("b".to_string())]
+            .iter()
+            .map(|m| m.ack_id.as_str())
+            .collect();
+        assert_eq!(b, vec!["2"]);
+
+        let none: Vec<&str> = groups[&None].iter().map(|m| m.ack_id.as_str()).collect();
+        assert_eq!(none, vec!["4"]);
+    }
+
+    #[test]
+    fn chunk_ack_ids_splits_into_chunk_size_groups() {
+        let ids: Vec<String> = (0..10).map(|i| i.to_string()).collect();
+
+        let chunks: Vec<Vec<String>> = Subscription::chunk_ack_ids(&ids, 4)
+            .map(|c| c.to_vec())
+            .collect();
+
+        assert_eq!(chunks.len(), 3);
+        assert_eq!(chunks[0].len(), 4);
+        assert_eq!(chunks[1].len(), 4);
+        assert_eq!(chunks[2].len(), 2);
+    }
+
+    #[test]
+    fn chunk_ack_ids_treats_zero_chunk_size_as_one() {
+        let ids = vec!["a".to_string(), "b".to_string()];
+
+        let chunks: Vec<Vec<String>> = Subscription::chunk_ack_ids(&ids, 0)
+            .map(|c| c.to_vec())
+            .collect();
+
+        assert_eq!(chunks, vec![vec!["a".to_string()], vec!["b".to_string()]]);
+    }
+
+    #[test]
+    fn should_backoff_is_true_for_an_empty_batch() {
+        let result: Result<Vec<RawMessage>, error::Error> = Ok(vec![]);
+        assert!(Subscription::should_backoff(&result));
+    }
+
+    #[test]
+    fn should_backoff_is_false_for_a_non_empty_batch() {
+        let result: Result<Vec<RawMessage>, error::Error> = Ok(vec![raw_message("1", None)]);
+        assert!(!Subscription::should_backoff(&result));
+    }
+
+    #[test]
+    fn should_backoff_is_true_for_an_error() {
+        let result: Result<Vec<RawMessage>, error::Error> = Err(error::Error::PubSub {
+            code: 503,
+            status: "UNAVAILABLE".to_string(),
+            message: "temporarily unavailable".to_string(),
+        });
+        assert!(Subscription::should_backoff(&result));
+    }
+}